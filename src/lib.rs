@@ -1,11 +1,13 @@
 use std::{mem, io, fs};
-use std::ffi::{CStr, CString};
+use std::ffi::CStr;
 use std::os::raw::{c_char, c_int};
 use std::fs::File;
 use std::io::Write;
 use std::time::Instant;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::sync::RwLock;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Once;
 
 extern crate libc;
 use libc::{dlsym, getpid, pthread_self};
@@ -33,8 +35,15 @@ macro_rules! wrap {
         #[no_mangle]
         pub extern "C" fn $name($( $arg_n: $arg_t ),*) -> $ret_t {
             unsafe {
-                let name_cstr = CString::new(stringify!($name)).unwrap();
-                let orig_fn: extern fn($( $arg_t ),*) -> $ret_t = mem::transmute(dlsym(RTLD_NEXT, name_cstr.as_ptr()));
+                static RESOLVED: AtomicPtr<libc::c_void> = AtomicPtr::new(std::ptr::null_mut());
+                static RESOLVE_ONCE: Once = Once::new();
+
+                RESOLVE_ONCE.call_once(|| {
+                    let name_cstr = CStr::from_bytes_with_nul(concat!(stringify!($name), "\0").as_bytes()).unwrap();
+                    RESOLVED.store(dlsym(RTLD_NEXT, name_cstr.as_ptr()), Ordering::Relaxed);
+                });
+
+                let orig_fn: extern fn($( $arg_t ),*) -> $ret_t = mem::transmute(RESOLVED.load(Ordering::Relaxed));
                 let $ret_n = orig_fn($( $arg_n ),*);
                 $code;
                 $ret_n
@@ -43,6 +52,18 @@ macro_rules! wrap {
     };
 }
 
+// Emits a human-readable line in the default format, or an NDJSON object when
+// INTERCEPT_FS_FORMAT=ndjson, built from the same structured fields.
+macro_rules! emit {
+    ($op:expr, $path:expr, $ret:expr, $text:expr, [$($k:expr => $v:expr),* $(,)?]) => {
+        if CONFIG.ndjson {
+            log_json($op, $path, $ret as i64, &[$(($k, $v)),*]);
+        } else {
+            log($text);
+        }
+    };
+}
+
 thread_local! {
     static LOG_FILE: File = unsafe {
         if let Err(err) = fs::create_dir("/tmp/intercepts") {
@@ -56,8 +77,43 @@ thread_local! {
     static BEGUN_AT: Instant = Instant::now();
 }
 
+#[derive(Clone)]
+struct FdInfo {
+    path: String,
+    flags: c_int,
+    opened_at: Instant,
+    bytes_read: u64,
+    bytes_written: u64,
+    seek_count: u64,
+}
+
 lazy_static! {
-    static ref RELEVANT_FILE_DESCRIPTORS: RwLock<HashSet<c_int>> = RwLock::new(HashSet::new());
+    static ref RELEVANT_FILE_DESCRIPTORS: RwLock<HashMap<c_int, FdInfo>> = RwLock::new(HashMap::new());
+}
+
+struct Config {
+    prefixes: Vec<String>,
+    ndjson: bool,
+}
+
+lazy_static! {
+    static ref CONFIG: Config = {
+        let prefixes = std::env::var("INTERCEPT_FS_PREFIX")
+            .unwrap_or_else(|_| "/tmp".to_string())
+            .split(':')
+            .filter(|prefix| !prefix.is_empty())
+            .map(|prefix| prefix.to_string())
+            .collect();
+        let ndjson = std::env::var("INTERCEPT_FS_FORMAT")
+            .map(|format| format == "ndjson")
+            .unwrap_or(false);
+        Config { prefixes, ndjson }
+    };
+}
+
+enum FieldValue {
+    Str(String),
+    Int(i64),
 }
 
 fn log(info: String) {
@@ -67,12 +123,89 @@ fn log(info: String) {
     });
 }
 
-fn log_op(op: &str, path: &str, info: String) -> bool {
-    if !path.starts_with("/tmp") || path[4..].starts_with("/intercepts") {
-        return false;
+fn json_str(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
     }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::json_str;
+
+    #[test]
+    fn plain_string_is_unchanged_but_quoted() {
+        assert_eq!(json_str("hello"), "\"hello\"");
+    }
+
+    #[test]
+    fn escapes_quotes_and_backslashes() {
+        assert_eq!(json_str("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+
+    #[test]
+    fn escapes_common_control_chars() {
+        assert_eq!(json_str("a\nb\rc\td"), "\"a\\nb\\rc\\td\"");
+    }
+
+    #[test]
+    fn escapes_other_control_chars_as_unicode_codepoints() {
+        assert_eq!(json_str("a\u{1}b"), "\"a\\u0001b\"");
+    }
+
+    #[test]
+    fn passes_through_multibyte_chars() {
+        assert_eq!(json_str("caf\u{e9} \u{1f600}"), "\"caf\u{e9} \u{1f600}\"");
+    }
+
+    #[test]
+    fn empty_string() {
+        assert_eq!(json_str(""), "\"\"");
+    }
+}
+
+fn log_json(op: &str, path: Option<&str>, ret: i64, fields: &[(&str, FieldValue)]) {
+    let time = BEGUN_AT.with(|time| Instant::now().duration_since(*time));
     let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
-    log(format!("{} {} {}, errno {}", op, path, info, errno));
+    let mut line = format!(
+        "{{\"ts_sec\":{},\"ts_nsec\":{},\"pid\":{},\"tid\":{},\"op\":{},\"ret\":{},\"errno\":{}",
+        time.as_secs(), time.subsec_nanos(), unsafe { getpid() }, unsafe { pthread_self() }, json_str(op), ret, errno
+    );
+    if let Some(path) = path {
+        line += &format!(",\"path\":{}", json_str(path));
+    }
+    for (key, value) in fields {
+        line += &format!(",\"{}\":{}", key, match value {
+            FieldValue::Str(s) => json_str(s),
+            FieldValue::Int(i) => i.to_string(),
+        });
+    }
+    line.push('}');
+    log(line);
+}
+
+fn log_op(op: &str, path: &str, ret: i64, text: String, fields: &[(&str, FieldValue)]) -> bool {
+    if !CONFIG.prefixes.iter().any(|prefix| path.starts_with(prefix.as_str())) || path.starts_with("/tmp/intercepts") {
+        return false;
+    }
+    if CONFIG.ndjson {
+        log_json(op, Some(path), ret, fields);
+    } else {
+        let errno = io::Error::last_os_error().raw_os_error().unwrap_or(0);
+        log(format!("{} {} {}, errno {}", op, path, text, errno));
+    }
     true
 }
 
@@ -85,58 +218,472 @@ unsafe fn stat64_info(buf: *mut libc::stat64, ret: c_int) -> String {
     format!("-> mode {} uid {} gid {} size {} -> {}", (*buf).st_mode, (*buf).st_uid, (*buf).st_gid, (*buf).st_size, ret)
 }
 
-unsafe fn c_str<'a>(ptr: *const c_char) -> &'a str {
-    CStr::from_ptr(ptr).to_str().unwrap()
+fn stat_fields(buf: *const libc::stat) -> Vec<(&'static str, FieldValue)> {
+    let st = unsafe { &*buf };
+    vec![
+        ("mode", FieldValue::Int(st.st_mode as i64)),
+        ("uid", FieldValue::Int(st.st_uid as i64)),
+        ("gid", FieldValue::Int(st.st_gid as i64)),
+        ("size", FieldValue::Int(st.st_size as i64)),
+    ]
+}
+
+#[cfg(not(target_os = "freebsd"))]
+fn stat64_fields(buf: *const libc::stat64) -> Vec<(&'static str, FieldValue)> {
+    let st = unsafe { &*buf };
+    vec![
+        ("mode", FieldValue::Int(st.st_mode as i64)),
+        ("uid", FieldValue::Int(st.st_uid as i64)),
+        ("gid", FieldValue::Int(st.st_gid as i64)),
+        ("size", FieldValue::Int(st.st_size as i64)),
+    ]
+}
+
+// Pathnames on Linux are arbitrary bytes, not necessarily valid UTF-8. Since this
+// library is LD_PRELOADed into an arbitrary target process, panicking here (e.g. via
+// CStr::to_str().unwrap()) would abort that process the moment it touches a non-UTF-8
+// path. Fall back to a lossy conversion instead of ever unwrapping.
+unsafe fn c_str(ptr: *const c_char) -> String {
+    CStr::from_ptr(ptr).to_string_lossy().into_owned()
+}
+
+unsafe fn resolve_at_path(dirfd: c_int, pathname: *const c_char) -> String {
+    let path = c_str(pathname);
+    if path.starts_with('/') {
+        return path;
+    }
+    if path.is_empty() {
+        return fs::read_link(format!("/proc/self/fd/{}", dirfd))
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| format!("<fd {}>", dirfd));
+    }
+    if dirfd == libc::AT_FDCWD {
+        return std::env::current_dir()
+            .map(|dir| dir.join(&path).to_string_lossy().into_owned())
+            .unwrap_or(path);
+    }
+    fs::read_link(format!("/proc/self/fd/{}", dirfd))
+        .map(|dir| dir.join(&path).to_string_lossy().into_owned())
+        .unwrap_or_else(|_| format!("<fd {}>/{}", dirfd, path))
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn statx_info(buf: *mut libc::statx, ret: c_int) -> String {
+    let stx = &*buf;
+    let mut attrs = Vec::new();
+    if stx.stx_mask & libc::STATX_TYPE != 0 { attrs.push("TYPE"); }
+    if stx.stx_mask & libc::STATX_MODE != 0 { attrs.push("MODE"); }
+    if stx.stx_mask & libc::STATX_SIZE != 0 { attrs.push("SIZE"); }
+    if stx.stx_mask & libc::STATX_MTIME != 0 { attrs.push("MTIME"); }
+    if stx.stx_mask & libc::STATX_BTIME != 0 { attrs.push("BTIME"); }
+
+    let mut info = format!("-> mask [{}]", attrs.join(","));
+    if stx.stx_mask & (libc::STATX_MODE | libc::STATX_TYPE) != 0 {
+        info += &format!(" mode {}", stx.stx_mode);
+    }
+    if stx.stx_mask & libc::STATX_UID != 0 {
+        info += &format!(" uid {}", stx.stx_uid);
+    }
+    if stx.stx_mask & libc::STATX_GID != 0 {
+        info += &format!(" gid {}", stx.stx_gid);
+    }
+    if stx.stx_mask & libc::STATX_SIZE != 0 {
+        info += &format!(" size {}", stx.stx_size);
+    }
+    if stx.stx_mask & libc::STATX_BTIME != 0 {
+        info += &format!(" btime {}.{}", stx.stx_btime.tv_sec, stx.stx_btime.tv_nsec);
+    }
+    info + &format!(" -> {}", ret)
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn statx_fields(buf: *mut libc::statx) -> Vec<(&'static str, FieldValue)> {
+    let stx = &*buf;
+    let mut mask = Vec::new();
+    if stx.stx_mask & libc::STATX_TYPE != 0 { mask.push("TYPE"); }
+    if stx.stx_mask & libc::STATX_MODE != 0 { mask.push("MODE"); }
+    if stx.stx_mask & libc::STATX_SIZE != 0 { mask.push("SIZE"); }
+    if stx.stx_mask & libc::STATX_MTIME != 0 { mask.push("MTIME"); }
+    if stx.stx_mask & libc::STATX_BTIME != 0 { mask.push("BTIME"); }
+    let mut fields = vec![("mask", FieldValue::Str(mask.join(",")))];
+    if stx.stx_mask & (libc::STATX_MODE | libc::STATX_TYPE) != 0 {
+        fields.push(("mode", FieldValue::Int(stx.stx_mode as i64)));
+    }
+    if stx.stx_mask & libc::STATX_UID != 0 {
+        fields.push(("uid", FieldValue::Int(stx.stx_uid as i64)));
+    }
+    if stx.stx_mask & libc::STATX_GID != 0 {
+        fields.push(("gid", FieldValue::Int(stx.stx_gid as i64)));
+    }
+    if stx.stx_mask & libc::STATX_SIZE != 0 {
+        fields.push(("size", FieldValue::Int(stx.stx_size as i64)));
+    }
+    if stx.stx_mask & libc::STATX_BTIME != 0 {
+        fields.push(("btime_sec", FieldValue::Int(stx.stx_btime.tv_sec as i64)));
+        fields.push(("btime_nsec", FieldValue::Int(stx.stx_btime.tv_nsec as i64)));
+    }
+    fields
 }
 
 wrap! {
     fn open:(path: *const c_char, flags: c_int, mode: c_int) -> ret: c_int {
-        if log_op("open", c_str(path), format!("(flags: {}, mode: {}) -> {}", flags, mode, ret)) && ret > 0 {
-            RELEVANT_FILE_DESCRIPTORS.write().unwrap().insert(ret);
+        let p = c_str(path);
+        if log_op("open", &p, ret as i64, format!("(flags: {}, mode: {}) -> {}", flags, mode, ret), &[
+            ("flags", FieldValue::Int(flags as i64)),
+            ("mode", FieldValue::Int(mode as i64)),
+        ]) && ret > 0 {
+            RELEVANT_FILE_DESCRIPTORS.write().unwrap().insert(ret, FdInfo {
+                path: p,
+                flags,
+                opened_at: Instant::now(),
+                bytes_read: 0,
+                bytes_written: 0,
+                seek_count: 0,
+            });
         }
     }
 
     fn close:(fd: c_int) -> ret: c_int {
         if ret == 0 {
-            if RELEVANT_FILE_DESCRIPTORS.write().unwrap().remove(&ret) {
-                log(format!("close {} -> 0", fd));
+            let removed = RELEVANT_FILE_DESCRIPTORS.write().unwrap().remove(&fd);
+            if let Some(info) = removed {
+                let open_secs = info.opened_at.elapsed().as_secs_f64();
+                emit!("close", Some(info.path.as_str()), 0, format!("close {} {} -> 0, read {} bytes, wrote {} bytes, {} seeks, open {:.6}s (flags: {})", fd, info.path, info.bytes_read, info.bytes_written, info.seek_count, open_secs, info.flags), [
+                    "fd" => FieldValue::Int(fd as i64),
+                    "bytes_read" => FieldValue::Int(info.bytes_read as i64),
+                    "bytes_written" => FieldValue::Int(info.bytes_written as i64),
+                    "seeks" => FieldValue::Int(info.seek_count as i64),
+                    "open_secs" => FieldValue::Str(format!("{:.6}", open_secs)),
+                    "flags" => FieldValue::Int(info.flags as i64),
+                ]);
+            }
+        }
+    }
+
+    fn read:(fd: c_int, buf: *mut libc::c_void, count: libc::size_t) -> ret: libc::ssize_t {
+        if ret >= 0 {
+            let updated = {
+                let mut descriptors = RELEVANT_FILE_DESCRIPTORS.write().unwrap();
+                descriptors.get_mut(&fd).map(|info| {
+                    info.bytes_read += ret as u64;
+                    (info.path.clone(), info.bytes_read)
+                })
+            };
+            if let Some((path, total_read)) = updated {
+                emit!("read", Some(path.as_str()), ret, format!("read {} {} requested {} got {} -> total read {}", fd, path, count, ret, total_read), [
+                    "fd" => FieldValue::Int(fd as i64),
+                    "size" => FieldValue::Int(count as i64),
+                    "total_bytes_read" => FieldValue::Int(total_read as i64),
+                ]);
+            }
+        }
+    }
+
+    fn write:(fd: c_int, buf: *const libc::c_void, count: libc::size_t) -> ret: libc::ssize_t {
+        if ret >= 0 {
+            let updated = {
+                let mut descriptors = RELEVANT_FILE_DESCRIPTORS.write().unwrap();
+                descriptors.get_mut(&fd).map(|info| {
+                    info.bytes_written += ret as u64;
+                    (info.path.clone(), info.bytes_written)
+                })
+            };
+            if let Some((path, total_written)) = updated {
+                emit!("write", Some(path.as_str()), ret, format!("write {} {} requested {} wrote {} -> total written {}", fd, path, count, ret, total_written), [
+                    "fd" => FieldValue::Int(fd as i64),
+                    "size" => FieldValue::Int(count as i64),
+                    "total_bytes_written" => FieldValue::Int(total_written as i64),
+                ]);
+            }
+        }
+    }
+
+    fn pread:(fd: c_int, buf: *mut libc::c_void, count: libc::size_t, offset: libc::off_t) -> ret: libc::ssize_t {
+        if ret >= 0 {
+            let updated = {
+                let mut descriptors = RELEVANT_FILE_DESCRIPTORS.write().unwrap();
+                descriptors.get_mut(&fd).map(|info| {
+                    info.bytes_read += ret as u64;
+                    (info.path.clone(), info.bytes_read)
+                })
+            };
+            if let Some((path, total_read)) = updated {
+                emit!("pread", Some(path.as_str()), ret, format!("pread {} {} offset {} requested {} got {} -> total read {}", fd, path, offset, count, ret, total_read), [
+                    "fd" => FieldValue::Int(fd as i64),
+                    "offset" => FieldValue::Int(offset as i64),
+                    "size" => FieldValue::Int(count as i64),
+                    "total_bytes_read" => FieldValue::Int(total_read as i64),
+                ]);
+            }
+        }
+    }
+
+    fn pwrite:(fd: c_int, buf: *const libc::c_void, count: libc::size_t, offset: libc::off_t) -> ret: libc::ssize_t {
+        if ret >= 0 {
+            let updated = {
+                let mut descriptors = RELEVANT_FILE_DESCRIPTORS.write().unwrap();
+                descriptors.get_mut(&fd).map(|info| {
+                    info.bytes_written += ret as u64;
+                    (info.path.clone(), info.bytes_written)
+                })
+            };
+            if let Some((path, total_written)) = updated {
+                emit!("pwrite", Some(path.as_str()), ret, format!("pwrite {} {} offset {} requested {} wrote {} -> total written {}", fd, path, offset, count, ret, total_written), [
+                    "fd" => FieldValue::Int(fd as i64),
+                    "offset" => FieldValue::Int(offset as i64),
+                    "size" => FieldValue::Int(count as i64),
+                    "total_bytes_written" => FieldValue::Int(total_written as i64),
+                ]);
+            }
+        }
+    }
+
+    fn lseek:(fd: c_int, offset: libc::off_t, whence: c_int) -> ret: libc::off_t {
+        let updated = {
+            let mut descriptors = RELEVANT_FILE_DESCRIPTORS.write().unwrap();
+            descriptors.get_mut(&fd).map(|info| {
+                info.seek_count += 1;
+                (info.path.clone(), info.seek_count)
+            })
+        };
+        if let Some((path, seeks)) = updated {
+            emit!("lseek", Some(path.as_str()), ret, format!("lseek {} {} whence {} offset {} -> {} (seek #{})", fd, path, whence, offset, ret, seeks), [
+                "fd" => FieldValue::Int(fd as i64),
+                "whence" => FieldValue::Int(whence as i64),
+                "offset" => FieldValue::Int(offset as i64),
+                "seeks" => FieldValue::Int(seeks as i64),
+            ]);
+        }
+    }
+
+    fn dup:(fd: c_int) -> ret: c_int {
+        if ret >= 0 {
+            let info = RELEVANT_FILE_DESCRIPTORS.read().unwrap().get(&fd).cloned();
+            if let Some(mut info) = info {
+                emit!("dup", Some(info.path.as_str()), ret, format!("dup {} -> {} {}", fd, ret, info.path), [
+                    "fd" => FieldValue::Int(fd as i64),
+                ]);
+                info.bytes_read = 0;
+                info.bytes_written = 0;
+                info.seek_count = 0;
+                RELEVANT_FILE_DESCRIPTORS.write().unwrap().insert(ret, info);
+            }
+        }
+    }
+
+    fn dup2:(oldfd: c_int, newfd: c_int) -> ret: c_int {
+        if ret >= 0 {
+            let info = RELEVANT_FILE_DESCRIPTORS.read().unwrap().get(&oldfd).cloned();
+            if let Some(mut info) = info {
+                emit!("dup2", Some(info.path.as_str()), ret, format!("dup2 {} -> {} {}", oldfd, ret, info.path), [
+                    "fd" => FieldValue::Int(oldfd as i64),
+                ]);
+                info.bytes_read = 0;
+                info.bytes_written = 0;
+                info.seek_count = 0;
+                RELEVANT_FILE_DESCRIPTORS.write().unwrap().insert(ret, info);
+            } else {
+                RELEVANT_FILE_DESCRIPTORS.write().unwrap().remove(&newfd);
+            }
+        }
+    }
+
+    fn fcntl:(fd: c_int, cmd: c_int, arg: c_int) -> ret: c_int {
+        if ret >= 0 && (cmd == libc::F_DUPFD || cmd == libc::F_DUPFD_CLOEXEC) {
+            let info = RELEVANT_FILE_DESCRIPTORS.read().unwrap().get(&fd).cloned();
+            if let Some(mut info) = info {
+                let op_name = if cmd == libc::F_DUPFD_CLOEXEC { "F_DUPFD_CLOEXEC" } else { "F_DUPFD" };
+                emit!("fcntl", Some(info.path.as_str()), ret, format!("fcntl({}) {} -> {} {}", op_name, fd, ret, info.path), [
+                    "fd" => FieldValue::Int(fd as i64),
+                ]);
+                info.bytes_read = 0;
+                info.bytes_written = 0;
+                info.seek_count = 0;
+                RELEVANT_FILE_DESCRIPTORS.write().unwrap().insert(ret, info);
             }
         }
     }
 
     fn mkdir:(path: *const c_char, mode: c_int) -> ret: c_int {
-        log_op("mkdir", c_str(path), format!("(mode: {}) -> {}", mode, ret));
+        log_op("mkdir", &c_str(path), ret as i64, format!("(mode: {}) -> {}", mode, ret), &[
+            ("mode", FieldValue::Int(mode as i64)),
+        ]);
     }
 
     fn symlink:(target: *const c_char, linkpath: *const c_char) -> ret: c_int {
-        log_op("symlink", c_str(linkpath), format!("-> {} -> {}", c_str(target), ret));
+        let target = c_str(target);
+        log_op("symlink", &c_str(linkpath), ret as i64, format!("-> {} -> {}", target, ret), &[
+            ("target", FieldValue::Str(target)),
+        ]);
     }
 
     fn __xstat:(ver: c_int, path: *const c_char, buf: *mut libc::stat) -> ret: c_int {
-        log_op("stat", c_str(path), stat_info(buf, ret));
+        log_op("stat", &c_str(path), ret as i64, stat_info(buf, ret), &stat_fields(buf));
     }
 
     fn stat:(path: *const c_char, buf: *mut libc::stat) -> ret: c_int {
-        log_op("stat", c_str(path), stat_info(buf, ret));
+        log_op("stat", &c_str(path), ret as i64, stat_info(buf, ret), &stat_fields(buf));
     }
 
     fn __lxstat:(ver: c_int, path: *const c_char, buf: *mut libc::stat) -> ret: c_int {
-        log_op("lstat", c_str(path), stat_info(buf, ret));
+        log_op("lstat", &c_str(path), ret as i64, stat_info(buf, ret), &stat_fields(buf));
     }
 
     fn lstat:(path: *const c_char, buf: *mut libc::stat) -> ret: c_int {
-        log_op("lstat", c_str(path), stat_info(buf, ret));
+        log_op("lstat", &c_str(path), ret as i64, stat_info(buf, ret), &stat_fields(buf));
     }
 
     fn __fxstat:(ver: c_int, fd: c_int, buf: *mut libc::stat) -> ret: c_int {
-        if RELEVANT_FILE_DESCRIPTORS.read().unwrap().contains(&fd) {
-            log(format!("fstat {} {}", fd, stat_info(buf, ret)));
+        let entry = {
+            let descriptors = RELEVANT_FILE_DESCRIPTORS.read().unwrap();
+            descriptors.get(&fd).map(|info| (info.path.clone(), info.flags))
+        };
+        if let Some((path, flags)) = entry {
+            let text = format!("fstat {} {} (flags: {}) {}", fd, path, flags, stat_info(buf, ret));
+            if CONFIG.ndjson {
+                let mut fields = stat_fields(buf);
+                fields.push(("fd", FieldValue::Int(fd as i64)));
+                fields.push(("flags", FieldValue::Int(flags as i64)));
+                log_json("fstat", Some(&path), ret as i64, &fields);
+            } else {
+                log(text);
+            }
         }
     }
 
     fn fstat:(fd: c_int, buf: *mut libc::stat) -> ret: c_int {
-        if RELEVANT_FILE_DESCRIPTORS.read().unwrap().contains(&fd) {
-            log(format!("fstat {} {}", fd, stat_info(buf, ret)));
+        let entry = {
+            let descriptors = RELEVANT_FILE_DESCRIPTORS.read().unwrap();
+            descriptors.get(&fd).map(|info| (info.path.clone(), info.flags))
+        };
+        if let Some((path, flags)) = entry {
+            let text = format!("fstat {} {} (flags: {}) {}", fd, path, flags, stat_info(buf, ret));
+            if CONFIG.ndjson {
+                let mut fields = stat_fields(buf);
+                fields.push(("fd", FieldValue::Int(fd as i64)));
+                fields.push(("flags", FieldValue::Int(flags as i64)));
+                log_json("fstat", Some(&path), ret as i64, &fields);
+            } else {
+                log(text);
+            }
+        }
+    }
+
+    fn openat:(dirfd: c_int, pathname: *const c_char, flags: c_int, mode: c_int) -> ret: c_int {
+        let path = resolve_at_path(dirfd, pathname);
+        if log_op("openat", &path, ret as i64, format!("(flags: {}, mode: {}) -> {}", flags, mode, ret), &[
+            ("flags", FieldValue::Int(flags as i64)),
+            ("mode", FieldValue::Int(mode as i64)),
+        ]) && ret >= 0 {
+            RELEVANT_FILE_DESCRIPTORS.write().unwrap().insert(ret, FdInfo {
+                path,
+                flags,
+                opened_at: Instant::now(),
+                bytes_read: 0,
+                bytes_written: 0,
+                seek_count: 0,
+            });
+        }
+    }
+
+    fn fstatat:(dirfd: c_int, pathname: *const c_char, buf: *mut libc::stat, flags: c_int) -> ret: c_int {
+        let path = resolve_at_path(dirfd, pathname);
+        log_op("fstatat", &path, ret as i64, stat_info(buf, ret), &stat_fields(buf));
+    }
+
+    fn __fxstatat:(ver: c_int, dirfd: c_int, pathname: *const c_char, buf: *mut libc::stat, flags: c_int) -> ret: c_int {
+        let path = resolve_at_path(dirfd, pathname);
+        log_op("fstatat", &path, ret as i64, stat_info(buf, ret), &stat_fields(buf));
+    }
+
+    fn renameat:(olddirfd: c_int, oldpath: *const c_char, newdirfd: c_int, newpath: *const c_char) -> ret: c_int {
+        let old = resolve_at_path(olddirfd, oldpath);
+        let new = resolve_at_path(newdirfd, newpath);
+        log_op("renameat", &old, ret as i64, format!("-> {} -> {}", new, ret), &[("target", FieldValue::Str(new.clone()))]);
+        log_op("renameat", &new, ret as i64, format!("<- {} -> {}", old, ret), &[("source", FieldValue::Str(old.clone()))]);
+    }
+
+    fn unlinkat:(dirfd: c_int, pathname: *const c_char, flags: c_int) -> ret: c_int {
+        let path = resolve_at_path(dirfd, pathname);
+        log_op("unlinkat", &path, ret as i64, format!("(flags: {}) -> {}", flags, ret), &[("flags", FieldValue::Int(flags as i64))]);
+    }
+
+    fn mkdirat:(dirfd: c_int, pathname: *const c_char, mode: c_int) -> ret: c_int {
+        let path = resolve_at_path(dirfd, pathname);
+        log_op("mkdirat", &path, ret as i64, format!("(mode: {}) -> {}", mode, ret), &[("mode", FieldValue::Int(mode as i64))]);
+    }
+
+    fn symlinkat:(target: *const c_char, newdirfd: c_int, linkpath: *const c_char) -> ret: c_int {
+        let path = resolve_at_path(newdirfd, linkpath);
+        let target = c_str(target);
+        log_op("symlinkat", &path, ret as i64, format!("-> {} -> {}", target, ret), &[("target", FieldValue::Str(target))]);
+    }
+
+    fn linkat:(olddirfd: c_int, oldpath: *const c_char, newdirfd: c_int, newpath: *const c_char, flags: c_int) -> ret: c_int {
+        let old = resolve_at_path(olddirfd, oldpath);
+        let new = resolve_at_path(newdirfd, newpath);
+        log_op("linkat", &old, ret as i64, format!("-> {} -> {}", new, ret), &[("target", FieldValue::Str(new.clone()))]);
+        log_op("linkat", &new, ret as i64, format!("<- {} -> {}", old, ret), &[("source", FieldValue::Str(old.clone()))]);
+    }
+
+    fn readlinkat:(dirfd: c_int, pathname: *const c_char, buf: *mut c_char, bufsiz: libc::size_t) -> ret: libc::ssize_t {
+        let path = resolve_at_path(dirfd, pathname);
+        if ret >= 0 {
+            let target = std::slice::from_raw_parts(buf as *const u8, ret as usize);
+            let target = String::from_utf8_lossy(target).into_owned();
+            log_op("readlinkat", &path, ret as i64, format!("-> {} -> {}", target, ret), &[("target", FieldValue::Str(target))]);
+        } else {
+            log_op("readlinkat", &path, ret as i64, format!("-> {}", ret), &[]);
+        }
+    }
+
+    fn rename:(oldpath: *const c_char, newpath: *const c_char) -> ret: c_int {
+        let old = c_str(oldpath);
+        let new = c_str(newpath);
+        log_op("rename", &old, ret as i64, format!("-> {} -> {}", new, ret), &[("target", FieldValue::Str(new.clone()))]);
+        log_op("rename", &new, ret as i64, format!("<- {} -> {}", old, ret), &[("source", FieldValue::Str(old.clone()))]);
+    }
+
+    fn unlink:(pathname: *const c_char) -> ret: c_int {
+        log_op("unlink", &c_str(pathname), ret as i64, format!("-> {}", ret), &[]);
+    }
+
+    fn rmdir:(pathname: *const c_char) -> ret: c_int {
+        log_op("rmdir", &c_str(pathname), ret as i64, format!("-> {}", ret), &[]);
+    }
+
+    fn link:(oldpath: *const c_char, newpath: *const c_char) -> ret: c_int {
+        let old = c_str(oldpath);
+        let new = c_str(newpath);
+        log_op("link", &old, ret as i64, format!("-> {} -> {}", new, ret), &[("target", FieldValue::Str(new.clone()))]);
+        log_op("link", &new, ret as i64, format!("<- {} -> {}", old, ret), &[("source", FieldValue::Str(old.clone()))]);
+    }
+
+    fn readlink:(pathname: *const c_char, buf: *mut c_char, bufsiz: libc::size_t) -> ret: libc::ssize_t {
+        let path = c_str(pathname);
+        if ret >= 0 {
+            let target = std::slice::from_raw_parts(buf as *const u8, ret as usize);
+            let target = String::from_utf8_lossy(target).into_owned();
+            log_op("readlink", &path, ret as i64, format!("-> {} -> {}", target, ret), &[("target", FieldValue::Str(target))]);
+        } else {
+            log_op("readlink", &path, ret as i64, format!("-> {}", ret), &[]);
+        }
+    }
+
+    fn truncate:(path: *const c_char, length: libc::off_t) -> ret: c_int {
+        log_op("truncate", &c_str(path), ret as i64, format!("(length: {}) -> {}", length, ret), &[("size", FieldValue::Int(length as i64))]);
+    }
+
+    fn ftruncate:(fd: c_int, length: libc::off_t) -> ret: c_int {
+        let path = {
+            let descriptors = RELEVANT_FILE_DESCRIPTORS.read().unwrap();
+            descriptors.get(&fd).map(|info| info.path.clone())
+        };
+        if let Some(path) = path {
+            emit!("ftruncate", Some(path.as_str()), ret, format!("ftruncate {} {} (length: {}) -> {}", fd, path, length, ret), [
+                "fd" => FieldValue::Int(fd as i64),
+                "size" => FieldValue::Int(length as i64),
+            ]);
         }
     }
 }
@@ -145,30 +692,84 @@ wrap! {
 #[cfg(not(target_os = "freebsd"))]
 wrap! {
     fn __xstat64:(ver: c_int, path: *const c_char, buf: *mut libc::stat64) -> ret: c_int {
-        log_op("stat64", c_str(path), stat64_info(buf, ret));
+        log_op("stat64", &c_str(path), ret as i64, stat64_info(buf, ret), &stat64_fields(buf));
     }
 
     fn stat64:(path: *const c_char, buf: *mut libc::stat64) -> ret: c_int {
-        log_op("stat64", c_str(path), stat64_info(buf, ret));
+        log_op("stat64", &c_str(path), ret as i64, stat64_info(buf, ret), &stat64_fields(buf));
     }
 
     fn __lxstat64:(ver: c_int, path: *const c_char, buf: *mut libc::stat64) -> ret: c_int {
-        log_op("lstat64", c_str(path), stat64_info(buf, ret));
+        log_op("lstat64", &c_str(path), ret as i64, stat64_info(buf, ret), &stat64_fields(buf));
     }
 
     fn lstat64:(path: *const c_char, buf: *mut libc::stat64) -> ret: c_int {
-        log_op("lstat64", c_str(path), stat64_info(buf, ret));
+        log_op("lstat64", &c_str(path), ret as i64, stat64_info(buf, ret), &stat64_fields(buf));
     }
 
     fn __fxstat64:(ver: c_int, fd: c_int, buf: *mut libc::stat64) -> ret: c_int {
-        if RELEVANT_FILE_DESCRIPTORS.read().unwrap().contains(&fd) {
-            log(format!("fstat {} {}", fd, stat64_info(buf, ret)));
+        let entry = {
+            let descriptors = RELEVANT_FILE_DESCRIPTORS.read().unwrap();
+            descriptors.get(&fd).map(|info| (info.path.clone(), info.flags))
+        };
+        if let Some((path, flags)) = entry {
+            let text = format!("fstat {} {} (flags: {}) {}", fd, path, flags, stat64_info(buf, ret));
+            if CONFIG.ndjson {
+                let mut fields = stat64_fields(buf);
+                fields.push(("fd", FieldValue::Int(fd as i64)));
+                fields.push(("flags", FieldValue::Int(flags as i64)));
+                log_json("fstat", Some(&path), ret as i64, &fields);
+            } else {
+                log(text);
+            }
         }
     }
 
     fn fstat64:(fd: c_int, buf: *mut libc::stat64) -> ret: c_int {
-        if RELEVANT_FILE_DESCRIPTORS.read().unwrap().contains(&fd) {
-            log(format!("fstat {} {}", fd, stat64_info(buf, ret)));
+        let entry = {
+            let descriptors = RELEVANT_FILE_DESCRIPTORS.read().unwrap();
+            descriptors.get(&fd).map(|info| (info.path.clone(), info.flags))
+        };
+        if let Some((path, flags)) = entry {
+            let text = format!("fstat {} {} (flags: {}) {}", fd, path, flags, stat64_info(buf, ret));
+            if CONFIG.ndjson {
+                let mut fields = stat64_fields(buf);
+                fields.push(("fd", FieldValue::Int(fd as i64)));
+                fields.push(("flags", FieldValue::Int(flags as i64)));
+                log_json("fstat", Some(&path), ret as i64, &fields);
+            } else {
+                log(text);
+            }
+        }
+    }
+
+    fn lseek64:(fd: c_int, offset: libc::off64_t, whence: c_int) -> ret: libc::off64_t {
+        let updated = {
+            let mut descriptors = RELEVANT_FILE_DESCRIPTORS.write().unwrap();
+            descriptors.get_mut(&fd).map(|info| {
+                info.seek_count += 1;
+                (info.path.clone(), info.seek_count)
+            })
+        };
+        if let Some((path, seeks)) = updated {
+            emit!("lseek64", Some(path.as_str()), ret, format!("lseek64 {} {} whence {} offset {} -> {} (seek #{})", fd, path, whence, offset, ret, seeks), [
+                "fd" => FieldValue::Int(fd as i64),
+                "whence" => FieldValue::Int(whence as i64),
+                "offset" => FieldValue::Int(offset as i64),
+                "seeks" => FieldValue::Int(seeks as i64),
+            ]);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+wrap! {
+    fn statx:(dirfd: c_int, pathname: *const c_char, flags: c_int, mask: libc::c_uint, buf: *mut libc::statx) -> ret: c_int {
+        let path = resolve_at_path(dirfd, pathname);
+        if ret == 0 {
+            log_op("statx", &path, ret as i64, statx_info(buf, ret), &statx_fields(buf));
+        } else {
+            log_op("statx", &path, ret as i64, format!("(mask: {}) -> {}", mask, ret), &[]);
         }
     }
 }